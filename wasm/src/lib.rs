@@ -2,15 +2,75 @@ use crate::utils::{extract_file_name, normalize_path, set_panic_hook};
 use ignore::gitignore::GitignoreBuilder;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::OnceLock;
-use tiktoken_rs::{cl100k_base, CoreBPE};
+use std::sync::{Mutex, OnceLock};
+use tiktoken_rs::{cl100k_base, o200k_base, p50k_base, r50k_base, CoreBPE};
 use wasm_bindgen::prelude::*;
 
+mod outline;
 mod utils;
 
-static TIKTOKEN_ENCODER: OnceLock<CoreBPE> = OnceLock::new();
-fn get_encoder() -> &'static CoreBPE {
-    TIKTOKEN_ENCODER.get_or_init(|| cl100k_base().expect("Failed to initialize tiktoken encoder"))
+const DEFAULT_ENCODING: &str = "cl100k_base";
+
+static TIKTOKEN_ENCODERS: OnceLock<Mutex<HashMap<String, &'static CoreBPE>>> = OnceLock::new();
+
+/// Builds the `CoreBPE` for a given tiktoken encoding name, falling back to
+/// `cl100k_base` for anything unrecognised.
+fn build_encoder(encoding: &str) -> CoreBPE {
+    match encoding {
+        "o200k_base" => o200k_base(),
+        "p50k_base" => p50k_base(),
+        "r50k_base" => r50k_base(),
+        _ => cl100k_base(),
+    }
+    .expect("Failed to initialize tiktoken encoder")
+}
+
+/// Returns the cached `CoreBPE` for `encoding`, initializing it on first use.
+/// `encoding` is resolved through `resolve_encoding` first, so the cache is
+/// keyed only by the known encoding names — an arbitrary caller-supplied
+/// string (this is reachable from `#[wasm_bindgen]` exports) can never grow
+/// the cache beyond that fixed, small set.
+fn get_encoder(encoding: &str) -> &'static CoreBPE {
+    let resolved = resolve_encoding(encoding);
+
+    let encoders = TIKTOKEN_ENCODERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut encoders = encoders.lock().expect("tiktoken encoder cache poisoned");
+
+    if let Some(encoder) = encoders.get(&resolved) {
+        return encoder;
+    }
+
+    let leaked: &'static CoreBPE = Box::leak(Box::new(build_encoder(&resolved)));
+    encoders.insert(resolved, leaked);
+    leaked
+}
+
+fn default_encoding() -> String {
+    DEFAULT_ENCODING.to_string()
+}
+
+/// Maps a model name to its tiktoken encoding, for callers that only know
+/// the model they're targeting rather than the encoding name itself.
+fn encoding_for_model(model: &str) -> &'static str {
+    match model {
+        "gpt-4o" | "gpt-4o-mini" | "o1" | "o1-mini" | "o1-preview" | "o3" | "o3-mini" => {
+            "o200k_base"
+        }
+        "gpt-4" | "gpt-4-turbo" | "gpt-3.5-turbo" | "text-embedding-ada-002" => "cl100k_base",
+        "text-davinci-003" | "text-davinci-002" | "code-davinci-002" => "p50k_base",
+        "davinci" | "curie" | "babbage" | "ada" => "r50k_base",
+        _ => DEFAULT_ENCODING,
+    }
+}
+
+/// Resolves a model or encoding name supplied by the caller to a tiktoken
+/// encoding name, accepting either form.
+#[wasm_bindgen]
+pub fn resolve_encoding(model_or_encoding: &str) -> String {
+    match model_or_encoding {
+        "o200k_base" | "cl100k_base" | "p50k_base" | "r50k_base" => model_or_encoding.to_string(),
+        other => encoding_for_model(other).to_string(),
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -41,9 +101,27 @@ pub struct ProcessingResult {
     pub processing_time_ms: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    Kept,
+    GitDirectory,
+    EmptyPath,
+    NotIncluded,
+    Ignored,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FilterDecision {
+    pub path: String,
+    pub kept: bool,
+    pub reason: FilterReason,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FilterResult {
     pub paths: Vec<String>,
+    pub decisions: Vec<FilterDecision>,
     #[serde(rename = "processingTimeMs")]
     pub processing_time_ms: f64,
 }
@@ -54,85 +132,179 @@ pub struct FileMetadata {
     pub size: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ProcessingOptions {
     #[serde(default = "default_true")]
     pub hide_empty_folders: bool,
     #[serde(default = "default_true")]
     pub show_token_count: bool,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
 }
 fn default_true() -> bool {
     true
 }
 
+impl Default for ProcessingOptions {
+    fn default() -> Self {
+        Self {
+            hide_empty_folders: true,
+            show_token_count: true,
+            encoding: default_encoding(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkdownMode {
+    #[default]
+    Full,
+    Outline,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Xml,
+    Json,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct MarkdownOptions {
     #[serde(default = "default_true")]
     pub include_path_headers: bool,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default)]
+    pub mode: MarkdownMode,
 }
 
-#[wasm_bindgen]
-pub fn filter_files(metadata_js: JsValue, gitignore_content: String) -> Result<JsValue, JsValue> {
-    set_panic_hook();
-    let start_time = js_sys::Date::now();
-
-    let metadata: Vec<FileMetadata> = serde_wasm_bindgen::from_value(metadata_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to parse metadata: {}", e)))?;
-
-    let mut gitignore_builder = GitignoreBuilder::new(".");
-    for (idx, raw_line) in gitignore_content.lines().enumerate() {
+/// Compiles gitignore-style pattern text (including `!`-negation, which
+/// `GitignoreBuilder`/`Gitignore` handle natively) into a matcher, warning
+/// on and skipping any line it can't parse.
+fn build_gitignore(content: &str) -> Result<ignore::gitignore::Gitignore, JsValue> {
+    let mut builder = GitignoreBuilder::new(".");
+    for (idx, raw_line) in content.lines().enumerate() {
         let line = raw_line.trim();
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
-        if let Err(e) = gitignore_builder.add_line(None, line) {
+        if let Err(e) = builder.add_line(None, line) {
             web_sys::console::warn_1(
                 &format!("Invalid gitignore pattern on line {}: {}", idx + 1, e).into(),
             );
         }
     }
-    let gitignore = gitignore_builder
+    builder
         .build()
-        .map_err(|e| JsValue::from_str(&format!("Failed to build gitignore: {}", e)))?;
+        .map_err(|e| JsValue::from_str(&format!("Failed to build gitignore: {}", e)))
+}
 
-    let kept_paths: Vec<String> = metadata
-        .into_iter()
-        .filter_map(|meta| {
-            let is_dir = meta.path.ends_with('/');
+/// Decides whether a single path is kept, against the ignore set and the
+/// optional include set (when `has_include_patterns`, a file must match an
+/// include pattern as well as not match the ignore set; directories skip the
+/// include check so included files still have a parent to attach to in the
+/// tree, but are still dropped when the ignore set matches them).
+fn classify_path(
+    meta: FileMetadata,
+    gitignore: &ignore::gitignore::Gitignore,
+    include_patterns: &ignore::gitignore::Gitignore,
+    has_include_patterns: bool,
+) -> FilterDecision {
+    let is_dir = meta.path.ends_with('/');
+
+    let relative_path = if let Some(first_slash) = meta.path.find('/') {
+        &meta.path[first_slash + 1..]
+    } else {
+        &meta.path
+    };
 
-            let relative_path = if let Some(first_slash) = meta.path.find('/') {
-                &meta.path[first_slash + 1..]
-            } else {
-                &meta.path
-            };
+    // Filter out the .git directory and its contents
+    if relative_path.starts_with(".git/") || relative_path == ".git" {
+        return FilterDecision {
+            path: meta.path,
+            kept: false,
+            reason: FilterReason::GitDirectory,
+        };
+    }
 
-            // Filter out the .git directory and its contents
-            if relative_path.starts_with(".git/") || relative_path == ".git" {
-                return None;
-            }
+    if relative_path.is_empty() {
+        return FilterDecision {
+            path: meta.path,
+            kept: false,
+            reason: FilterReason::EmptyPath,
+        };
+    }
 
-            if relative_path.is_empty() {
-                return None;
-            }
+    if has_include_patterns
+        && !is_dir
+        && !include_patterns
+            .matched_path_or_any_parents(relative_path, false)
+            .is_ignore()
+    {
+        return FilterDecision {
+            path: meta.path,
+            kept: false,
+            reason: FilterReason::NotIncluded,
+        };
+    }
 
-            if gitignore
-                .matched_path_or_any_parents(relative_path, is_dir)
-                .is_ignore()
-            {
-                return None;
-            }
+    if gitignore
+        .matched_path_or_any_parents(relative_path, is_dir)
+        .is_ignore()
+    {
+        return FilterDecision {
+            path: meta.path,
+            kept: false,
+            reason: FilterReason::Ignored,
+        };
+    }
 
-            if is_dir {
-                return Some(meta.path);
-            }
+    FilterDecision {
+        path: meta.path,
+        kept: true,
+        reason: FilterReason::Kept,
+    }
+}
 
-            Some(meta.path)
-        })
+#[wasm_bindgen]
+pub fn filter_files(
+    metadata_js: JsValue,
+    gitignore_content: String,
+    include_content: String,
+) -> Result<JsValue, JsValue> {
+    set_panic_hook();
+    let start_time = js_sys::Date::now();
+
+    let metadata: Vec<FileMetadata> = serde_wasm_bindgen::from_value(metadata_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse metadata: {}", e)))?;
+
+    let gitignore = build_gitignore(&gitignore_content)?;
+    let include_patterns = build_gitignore(&include_content)?;
+    let has_include_patterns = include_content
+        .lines()
+        .any(|line| !line.trim().is_empty() && !line.trim().starts_with('#'));
+
+    let decisions: Vec<FilterDecision> = metadata
+        .into_iter()
+        .map(|meta| classify_path(meta, &gitignore, &include_patterns, has_include_patterns))
+        .collect();
+
+    let kept_paths: Vec<String> = decisions
+        .iter()
+        .filter(|decision| decision.kept)
+        .map(|decision| decision.path.clone())
         .collect();
 
     let processing_time = js_sys::Date::now() - start_time;
     let result = FilterResult {
         paths: kept_paths,
+        decisions,
         processing_time_ms: processing_time,
     };
 
@@ -159,7 +331,7 @@ pub fn process_files(files_js: JsValue, options_js: JsValue) -> Result<JsValue,
         total_size += size;
 
         let tokens = if options.show_token_count {
-            get_encoder()
+            get_encoder(&options.encoding)
                 .encode_with_special_tokens(&file.content)
                 .len() as u32
         } else {
@@ -313,28 +485,286 @@ pub fn recalculate_counts(tree_js: JsValue, options_js: JsValue) -> Result<JsVal
         .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PackingPriority {
+    #[default]
+    SmallestFirst,
+    LargestFirst,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BudgetPackingOptions {
+    pub max_tokens: u32,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default)]
+    pub priority: PackingPriority,
+    #[serde(default)]
+    pub weights: HashMap<String, i64>,
+    #[serde(default = "default_true")]
+    pub include_path_headers: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BudgetPackResult {
+    pub included: Vec<String>,
+    pub excluded: Vec<String>,
+    pub used_tokens: u32,
+    pub budget_tokens: u32,
+}
+
+struct BudgetCandidate {
+    path: String,
+    tokens: u32,
+    weight: i64,
+}
+
+/// Token cost of the exact markdown `merge_files_to_markdown` emits for a
+/// single file (path header, fences, and content together). Encoding the
+/// header/fence overhead and the content separately and summing them is not
+/// exact: BPE tokenizes the text around that boundary differently once it is
+/// concatenated, so the sum of the parts can differ from the real cost.
+fn markdown_file_tokens(
+    path: &str,
+    language: &str,
+    content: &str,
+    include_path_headers: bool,
+    encoder: &CoreBPE,
+) -> u32 {
+    let mut rendered = String::new();
+    if include_path_headers {
+        rendered.push_str(&format!("#### File: `{}`\n", path));
+    }
+    rendered.push_str(&format!("```{}\n", language));
+    rendered.push_str(content.trim());
+    rendered.push_str("\n```\n\n");
+    encoder.encode_with_special_tokens(&rendered).len() as u32
+}
+
+/// Greedily selects which candidates fit within `max_tokens`, in priority
+/// order (explicit weights take precedence over `priority` when any file has
+/// a non-default weight), stopping as soon as a candidate would overflow the
+/// budget rather than skipping ahead to a smaller one later in the order.
+fn pack_candidates(
+    mut candidates: Vec<BudgetCandidate>,
+    max_tokens: u32,
+    priority: PackingPriority,
+) -> (Vec<String>, Vec<String>, u32) {
+    let has_weights = candidates.iter().any(|c| c.weight != 0);
+    candidates.sort_by(|a, b| {
+        if has_weights {
+            b.weight
+                .cmp(&a.weight)
+                .then_with(|| a.tokens.cmp(&b.tokens))
+        } else {
+            match priority {
+                PackingPriority::SmallestFirst => a.tokens.cmp(&b.tokens),
+                PackingPriority::LargestFirst => b.tokens.cmp(&a.tokens),
+            }
+        }
+    });
+
+    let mut included = Vec::new();
+    let mut excluded = Vec::new();
+    let mut used_tokens: u32 = 0;
+
+    let mut budget_full = false;
+
+    for candidate in candidates {
+        let next_total = used_tokens + candidate.tokens;
+        if !budget_full && next_total <= max_tokens {
+            used_tokens = next_total;
+            included.push(candidate.path);
+        } else {
+            budget_full = true;
+            excluded.push(candidate.path);
+        }
+    }
+
+    (included, excluded, used_tokens)
+}
+
+/// Greedily selects which files fit within `max_tokens` of merged markdown
+/// output, so the caller can trim a file set down to an LLM's context window.
 #[wasm_bindgen]
-pub fn merge_files_to_markdown(files_js: JsValue, options_js: JsValue) -> Result<String, JsValue> {
+pub fn pack_files_to_budget(files_js: JsValue, options_js: JsValue) -> Result<JsValue, JsValue> {
     set_panic_hook();
+
     let files: Vec<FileInput> = serde_wasm_bindgen::from_value(files_js)
         .map_err(|e| JsValue::from_str(&format!("Failed to parse files: {}", e)))?;
-    let options: MarkdownOptions = serde_wasm_bindgen::from_value(options_js).unwrap_or_default();
+    let options: BudgetPackingOptions = serde_wasm_bindgen::from_value(options_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse options: {}", e)))?;
+
+    let encoder = get_encoder(&options.encoding);
+
+    let candidates: Vec<BudgetCandidate> = files
+        .iter()
+        .map(|file| {
+            let language = detect_language(&file.path);
+            let tokens = markdown_file_tokens(
+                &file.path,
+                language,
+                &file.content,
+                options.include_path_headers,
+                encoder,
+            );
+            let weight = options.weights.get(&file.path).copied().unwrap_or(0);
+            BudgetCandidate {
+                path: file.path.clone(),
+                tokens,
+                weight,
+            }
+        })
+        .collect();
 
-    if files.is_empty() {
-        return Ok(String::new());
+    let (included, excluded, used_tokens) =
+        pack_candidates(candidates, options.max_tokens, options.priority);
+
+    let result = BudgetPackResult {
+        included,
+        excluded,
+        used_tokens,
+        budget_tokens: options.max_tokens,
+    };
+
+    serde_wasm_bindgen::to_value(&result)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
+}
+
+/// A file with its language detected and its content transformed to the
+/// requested `MarkdownMode`, ready to be rendered into any export format.
+struct RenderedFile {
+    path: String,
+    language: &'static str,
+    content: String,
+}
+
+/// Escapes text for use inside XML element content.
+fn escape_xml_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            // XML parsers normalize a bare `\r` (and `\r\n`) to `\n` on input,
+            // so an unescaped `\r` would silently change the file's content;
+            // encode it as a character reference to round-trip it exactly.
+            '\r' => escaped.push_str("&#13;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes text for use inside a double-quoted XML attribute value.
+fn escape_xml_attr(text: &str) -> String {
+    let mut escaped = escape_xml_text(text);
+    escaped = escaped.replace('"', "&quot;").replace('\'', "&apos;");
+    escaped
+}
+
+/// Escapes text for use inside a double-quoted JSON string.
+fn escape_json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
     }
+    escaped
+}
 
+fn render_markdown(files: &[RenderedFile], include_path_headers: bool) -> String {
     let mut output = String::new();
     for file in files {
-        let language = detect_language(&file.path);
-        if options.include_path_headers {
+        if include_path_headers {
             output.push_str(&format!("#### File: `{}`\n", file.path));
         }
-        output.push_str(&format!("```{}\n", language));
+        output.push_str(&format!("```{}\n", file.language));
         output.push_str(file.content.trim());
         output.push_str("\n```\n\n");
     }
-    Ok(output.trim().to_string())
+    output.trim().to_string()
+}
+
+fn render_xml(files: &[RenderedFile]) -> String {
+    let mut output = String::from("<documents>\n");
+    for file in files {
+        output.push_str(&format!(
+            "  <file path=\"{}\">\n",
+            escape_xml_attr(&file.path)
+        ));
+        output.push_str(&escape_xml_text(file.content.trim()));
+        output.push_str("\n  </file>\n");
+    }
+    output.push_str("</documents>");
+    output
+}
+
+fn render_json(files: &[RenderedFile], encoding: &str) -> String {
+    let encoder = get_encoder(encoding);
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        let token_count = encoder.encode_with_special_tokens(&file.content).len();
+        entries.push(format!(
+            "{{\"path\":\"{}\",\"language\":\"{}\",\"content\":\"{}\",\"token_count\":{}}}",
+            escape_json_string(&file.path),
+            escape_json_string(file.language),
+            escape_json_string(&file.content),
+            token_count
+        ));
+    }
+    format!("[{}]", entries.join(","))
+}
+
+/// Merges files into the export format selected by `options.format`
+/// (markdown by default, plus XML and JSON), optionally compressing each
+/// file to its structural outline first.
+#[wasm_bindgen]
+pub fn merge_files_to_markdown(files_js: JsValue, options_js: JsValue) -> Result<String, JsValue> {
+    set_panic_hook();
+    let files: Vec<FileInput> = serde_wasm_bindgen::from_value(files_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse files: {}", e)))?;
+    let options: MarkdownOptions = serde_wasm_bindgen::from_value(options_js).unwrap_or_default();
+
+    if files.is_empty() {
+        return Ok(match options.format {
+            ExportFormat::Json => "[]".to_string(),
+            _ => String::new(),
+        });
+    }
+
+    let rendered: Vec<RenderedFile> = files
+        .into_iter()
+        .map(|file| {
+            let language = detect_language(&file.path);
+            let content = if options.mode == MarkdownMode::Outline {
+                outline::extract_outline(language, &file.content).unwrap_or(file.content)
+            } else {
+                file.content
+            };
+            RenderedFile {
+                path: file.path,
+                language,
+                content,
+            }
+        })
+        .collect();
+
+    Ok(match options.format {
+        ExportFormat::Markdown => render_markdown(&rendered, options.include_path_headers),
+        ExportFormat::Xml => render_xml(&rendered),
+        ExportFormat::Json => render_json(&rendered, &options.encoding),
+    })
 }
 
 fn detect_language(path: &str) -> &'static str {
@@ -376,3 +806,180 @@ fn detect_language(path: &str) -> &'static str {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(path: &str, tokens: u32, weight: i64) -> BudgetCandidate {
+        BudgetCandidate {
+            path: path.to_string(),
+            tokens,
+            weight,
+        }
+    }
+
+    #[test]
+    fn pack_candidates_fills_smallest_first() {
+        let candidates = vec![
+            candidate("a", 5, 0),
+            candidate("b", 3, 0),
+            candidate("c", 4, 0),
+        ];
+        let (included, excluded, used) =
+            pack_candidates(candidates, 7, PackingPriority::SmallestFirst);
+        assert_eq!(included, vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(excluded, vec!["a".to_string()]);
+        assert_eq!(used, 7);
+    }
+
+    #[test]
+    fn pack_candidates_fills_largest_first() {
+        let candidates = vec![
+            candidate("a", 5, 0),
+            candidate("b", 3, 0),
+            candidate("c", 4, 0),
+        ];
+        let (included, excluded, used) =
+            pack_candidates(candidates, 5, PackingPriority::LargestFirst);
+        assert_eq!(included, vec!["a".to_string()]);
+        assert_eq!(excluded, vec!["c".to_string(), "b".to_string()]);
+        assert_eq!(used, 5);
+    }
+
+    #[test]
+    fn pack_candidates_stops_rather_than_backfilling_a_smaller_later_candidate() {
+        // "b" doesn't fit after "a", and packing doesn't skip ahead to "c"
+        // even though "c" alone would fit in the remaining budget.
+        let candidates = vec![
+            candidate("a", 4, 0),
+            candidate("b", 4, 0),
+            candidate("c", 1, 0),
+        ];
+        let (included, excluded, used) =
+            pack_candidates(candidates, 5, PackingPriority::LargestFirst);
+        assert_eq!(included, vec!["a".to_string()]);
+        assert_eq!(excluded, vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(used, 4);
+    }
+
+    #[test]
+    fn pack_candidates_prefers_explicit_weights_over_priority() {
+        let candidates = vec![candidate("a", 5, 1), candidate("b", 3, 0)];
+        let (included, excluded, _) =
+            pack_candidates(candidates, 5, PackingPriority::SmallestFirst);
+        assert_eq!(included, vec!["a".to_string()]);
+        assert_eq!(excluded, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn markdown_file_tokens_matches_the_exact_rendered_text() {
+        let encoder = get_encoder(DEFAULT_ENCODING);
+        let rendered = "#### File: `a/b/c.py`\n```python\nprint(\"hi\")\n```\n\n";
+        let expected = encoder.encode_with_special_tokens(rendered).len() as u32;
+        let actual = markdown_file_tokens("a/b/c.py", "python", "print(\"hi\")", true, encoder);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn escape_xml_text_escapes_reserved_characters_and_bare_cr() {
+        let escaped = escape_xml_text("a < b & c > d\r\n");
+        assert_eq!(escaped, "a &lt; b &amp; c &gt; d&#13;\n");
+    }
+
+    #[test]
+    fn escape_xml_attr_also_escapes_quotes() {
+        let escaped = escape_xml_attr("say \"hi\" it's me");
+        assert_eq!(escaped, "say &quot;hi&quot; it&apos;s me");
+    }
+
+    #[test]
+    fn escape_json_string_escapes_control_characters() {
+        let escaped = escape_json_string("line1\nline2\t\"quoted\"\\end");
+        assert_eq!(escaped, "line1\\nline2\\t\\\"quoted\\\"\\\\end");
+    }
+
+    #[test]
+    fn render_xml_wraps_each_file_and_escapes_its_path_and_content() {
+        let files = vec![RenderedFile {
+            path: "a&b.rs".to_string(),
+            language: "rust",
+            content: "fn main() {}".to_string(),
+        }];
+        let xml = render_xml(&files);
+        assert!(xml.starts_with("<documents>\n"));
+        assert!(xml.contains("<file path=\"a&amp;b.rs\">"));
+        assert!(xml.contains("fn main() {}"));
+        assert!(xml.ends_with("</documents>"));
+    }
+
+    #[test]
+    fn render_json_includes_token_counts_for_each_file() {
+        let files = vec![RenderedFile {
+            path: "a.rs".to_string(),
+            language: "rust",
+            content: "fn main() {}".to_string(),
+        }];
+        let json = render_json(&files, DEFAULT_ENCODING);
+        assert!(json.starts_with('['));
+        assert!(json.contains("\"path\":\"a.rs\""));
+        assert!(json.contains("\"language\":\"rust\""));
+        assert!(json.contains("\"token_count\":"));
+    }
+
+    fn meta(path: &str) -> FileMetadata {
+        FileMetadata {
+            path: path.to_string(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn classify_path_drops_the_git_directory() {
+        let gitignore = build_gitignore("").unwrap();
+        let include = build_gitignore("").unwrap();
+        let decision = classify_path(meta("repo/.git/HEAD"), &gitignore, &include, false);
+        assert!(!decision.kept);
+        assert_eq!(decision.reason, FilterReason::GitDirectory);
+    }
+
+    #[test]
+    fn classify_path_applies_gitignore_patterns() {
+        let gitignore = build_gitignore("*.log\n").unwrap();
+        let include = build_gitignore("").unwrap();
+        let decision = classify_path(meta("repo/debug.log"), &gitignore, &include, false);
+        assert!(!decision.kept);
+        assert_eq!(decision.reason, FilterReason::Ignored);
+    }
+
+    #[test]
+    fn classify_path_excludes_files_outside_the_include_set() {
+        let gitignore = build_gitignore("").unwrap();
+        let include = build_gitignore("*.rs\n").unwrap();
+        let decision = classify_path(meta("repo/src/main.rs"), &gitignore, &include, true);
+        assert!(decision.kept);
+        assert_eq!(decision.reason, FilterReason::Kept);
+
+        let decision = classify_path(meta("repo/README.md"), &gitignore, &include, true);
+        assert!(!decision.kept);
+        assert_eq!(decision.reason, FilterReason::NotIncluded);
+    }
+
+    #[test]
+    fn classify_path_keeps_directories_even_when_not_individually_included() {
+        let gitignore = build_gitignore("").unwrap();
+        let include = build_gitignore("*.rs\n").unwrap();
+        let decision = classify_path(meta("repo/src/"), &gitignore, &include, true);
+        assert!(decision.kept);
+        assert_eq!(decision.reason, FilterReason::Kept);
+    }
+
+    #[test]
+    fn classify_path_respects_negated_patterns() {
+        let gitignore = build_gitignore("*.log\n!keep.log\n").unwrap();
+        let include = build_gitignore("").unwrap();
+        let decision = classify_path(meta("repo/keep.log"), &gitignore, &include, false);
+        assert!(decision.kept);
+        assert_eq!(decision.reason, FilterReason::Kept);
+    }
+}
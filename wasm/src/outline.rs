@@ -0,0 +1,379 @@
+//! Structural "outline" extraction: parses a source file with tree-sitter and
+//! reconstructs only its declarations (functions, types, imports, top-level
+//! constants) with bodies collapsed to a placeholder, so large files can be
+//! included in an LLM context window without their implementation detail.
+
+use std::collections::{HashMap, HashSet};
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, StreamingIterator};
+
+/// Declaration node kinds to capture, per language, as a tree-sitter query.
+/// Each query captures the declaration node itself as `@decl`. Queries are
+/// intentionally allowed to match at any depth (e.g. a method inside an
+/// `impl` block matches the same as a free function) — `extract_outline`
+/// nests each capture under its nearest captured ancestor, so a method
+/// still renders inside the `impl`/`class`/`interface` that contains it,
+/// while a local declaration inside a function/method body (whose nearest
+/// captured ancestor is that function, not a container) is dropped.
+fn grammar_for(language_tag: &str) -> Option<(Language, &'static str)> {
+    match language_tag {
+        "rust" => Some((
+            tree_sitter_rust::LANGUAGE.into(),
+            r#"
+            [
+              (function_item)
+              (struct_item)
+              (enum_item)
+              (trait_item)
+              (impl_item)
+              (mod_item)
+              (const_item)
+              (static_item)
+              (use_declaration)
+            ] @decl
+            "#,
+        )),
+        "javascript" | "jsx" => Some((
+            tree_sitter_javascript::LANGUAGE.into(),
+            r#"
+            [
+              (function_declaration)
+              (class_declaration)
+              (method_definition)
+              (lexical_declaration)
+              (import_statement)
+            ] @decl
+            "#,
+        )),
+        "typescript" => Some((tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(), TS_QUERY)),
+        "tsx" => Some((tree_sitter_typescript::LANGUAGE_TSX.into(), TS_QUERY)),
+        "python" => Some((
+            tree_sitter_python::LANGUAGE.into(),
+            r#"
+            [
+              (function_definition)
+              (class_definition)
+              (import_statement)
+              (import_from_statement)
+            ] @decl
+            "#,
+        )),
+        "go" => Some((
+            tree_sitter_go::LANGUAGE.into(),
+            r#"
+            [
+              (function_declaration)
+              (method_declaration)
+              (type_declaration)
+              (const_declaration)
+              (var_declaration)
+              (import_declaration)
+            ] @decl
+            "#,
+        )),
+        _ => None,
+    }
+}
+
+const TS_QUERY: &str = r#"
+[
+  (function_declaration)
+  (class_declaration)
+  (interface_declaration)
+  (type_alias_declaration)
+  (enum_declaration)
+  (lexical_declaration)
+  (import_statement)
+  (method_definition)
+  (method_signature)
+  (property_signature)
+] @decl
+"#;
+
+/// Languages whose declarations end with a `:` before an indented body
+/// rather than a brace-delimited block.
+fn body_placeholder(language_tag: &str) -> &'static str {
+    match language_tag {
+        "python" => "...",
+        _ => "{ ... }",
+    }
+}
+
+/// Finds the node's body/block child, if it has one, by the field names
+/// tree-sitter grammars conventionally use for a declaration's body.
+fn body_of(node: Node) -> Option<Node> {
+    node.child_by_field_name("body")
+}
+
+/// A sibling node kind that decorates the following declaration rather than
+/// standing on its own: a doc/line comment, or an attribute/decorator
+/// (`#[derive(...)]`, `#[wasm_bindgen]`, `@decorator`).
+fn is_leading_annotation(kind: &str) -> bool {
+    kind.contains("comment") || kind == "attribute_item" || kind == "decorator"
+}
+
+/// Leading comments and attributes/decorators directly attached to `node`
+/// (walking past attributes to reach a doc comment above them, e.g.
+/// `/// docs` followed by `#[wasm_bindgen]`), rendered in source order ahead
+/// of the declaration's header.
+fn leading_annotations(node: Node, source: &[u8]) -> String {
+    let mut nodes = Vec::new();
+    let mut sibling = node.prev_sibling();
+    while let Some(s) = sibling {
+        if is_leading_annotation(s.kind()) {
+            nodes.push(s);
+            sibling = s.prev_sibling();
+        } else {
+            break;
+        }
+    }
+    nodes.reverse();
+    nodes
+        .into_iter()
+        .map(|n| String::from_utf8_lossy(&source[n.start_byte()..n.end_byte()]).to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Declaration kinds whose body holds further member declarations (methods,
+/// associated items, nested types) that should be spliced back into the
+/// placeholder rather than discarded with the rest of the body.
+fn is_container_kind(kind: &str) -> bool {
+    matches!(
+        kind,
+        "impl_item"
+            | "mod_item"
+            | "trait_item"
+            | "class_declaration"
+            | "class_definition"
+            | "interface_declaration"
+    )
+}
+
+/// Indents every non-empty line of `text` by `prefix`.
+fn indent_lines(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wraps a container's rendered member text back into its header, using a
+/// brace block for most languages and an indented suite for Python.
+fn wrap_container_body(header: &str, inner: &str, language_tag: &str) -> String {
+    let indented = indent_lines(inner, "    ");
+    match language_tag {
+        "python" => format!("{}\n{}", header, indented),
+        _ => format!("{} {{\n{}\n}}", header, indented),
+    }
+}
+
+/// Renders a single declaration node as its leading annotations, then its
+/// header text (signature, up to the body). A container (`impl`/`mod`/
+/// `class`/`interface`) splices its captured members (from `children_map`)
+/// back into the body instead of collapsing them; any other declaration's
+/// body is replaced with a placeholder, dropping anything nested inside it
+/// (e.g. a local variable inside a function/method body).
+fn render_node(
+    node: Node,
+    source: &[u8],
+    language_tag: &str,
+    children_map: &HashMap<usize, Vec<Node>>,
+) -> String {
+    let annotations = leading_annotations(node, source);
+
+    let header = match body_of(node) {
+        Some(body) => {
+            let header_bytes = &source[node.start_byte()..body.start_byte()];
+            String::from_utf8_lossy(header_bytes).trim_end().to_string()
+        }
+        None => String::from_utf8_lossy(&source[node.start_byte()..node.end_byte()]).to_string(),
+    };
+
+    let rendered = match body_of(node) {
+        None => header,
+        Some(_) if is_container_kind(node.kind()) => {
+            let mut children = children_map.get(&node.id()).cloned().unwrap_or_default();
+            if children.is_empty() {
+                format!("{} {}", header, body_placeholder(language_tag))
+            } else {
+                children.sort_by_key(|child| child.start_byte());
+                let inner = children
+                    .into_iter()
+                    .map(|child| render_node(child, source, language_tag, children_map))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                wrap_container_body(&header, &inner, language_tag)
+            }
+        }
+        Some(_) => format!("{} {}", header, body_placeholder(language_tag)),
+    };
+
+    if annotations.is_empty() {
+        rendered
+    } else {
+        format!("{}\n{}", annotations, rendered)
+    }
+}
+
+/// Parses `source` with the tree-sitter grammar for `language_tag` and
+/// returns its structural skeleton: declarations with bodies collapsed to a
+/// placeholder, except that container declarations (`impl`/`mod`/`class`/
+/// `interface`) keep their member signatures nested inside. Returns `None`
+/// for languages without a bundled grammar, so callers can fall back to the
+/// full file content.
+pub fn extract_outline(language_tag: &str, source: &str) -> Option<String> {
+    let (language, query_src) = grammar_for(language_tag)?;
+
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(source, None)?;
+    let query = Query::new(&language, query_src).ok()?;
+
+    let bytes = source.as_bytes();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), bytes);
+
+    let mut captured_nodes: Vec<Node> = Vec::new();
+    while let Some(m) = matches.next() {
+        for capture in m.captures {
+            captured_nodes.push(capture.node);
+        }
+    }
+    captured_nodes.sort_by_key(|node| node.start_byte());
+    captured_nodes.dedup_by_key(|node| node.id());
+
+    let captured_ids: HashSet<usize> = captured_nodes.iter().map(|node| node.id()).collect();
+
+    // Nest each capture under its nearest captured ancestor (e.g. a method
+    // under its enclosing `impl`/`class`), so `render_node` can tell genuine
+    // members apart from top-level declarations.
+    let mut children_map: HashMap<usize, Vec<Node>> = HashMap::new();
+    let mut top_level: Vec<Node> = Vec::new();
+
+    for &node in &captured_nodes {
+        let mut ancestor = node.parent();
+        let mut nearest_captured_ancestor = None;
+        while let Some(candidate) = ancestor {
+            if captured_ids.contains(&candidate.id()) {
+                nearest_captured_ancestor = Some(candidate);
+                break;
+            }
+            ancestor = candidate.parent();
+        }
+
+        match nearest_captured_ancestor {
+            Some(parent) => children_map.entry(parent.id()).or_default().push(node),
+            None => top_level.push(node),
+        }
+    }
+
+    top_level.sort_by_key(|node| node.start_byte());
+
+    Some(
+        top_level
+            .into_iter()
+            .map(|node| render_node(node, bytes, language_tag, &children_map))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_outline;
+
+    #[test]
+    fn rust_impl_methods_are_kept_once_nested_inside_the_impl() {
+        let source = r#"
+impl Foo {
+    pub fn bar(&self) -> u32 {
+        let local = 1;
+        local
+    }
+}
+"#;
+        let outline = extract_outline("rust", source).expect("rust grammar is supported");
+        assert_eq!(outline.matches("fn bar").count(), 1);
+        assert!(outline.contains("impl Foo"));
+        assert!(outline.contains("pub fn bar(&self) -> u32 { ... }"));
+        assert!(!outline.contains("let local"));
+    }
+
+    #[test]
+    fn javascript_local_declarations_do_not_leak() {
+        let source = r#"
+function outer() {
+    const local = 1;
+    return local;
+}
+"#;
+        let outline =
+            extract_outline("javascript", source).expect("javascript grammar is supported");
+        assert_eq!(outline.matches("function outer").count(), 1);
+        assert!(!outline.contains("const local"));
+    }
+
+    #[test]
+    fn javascript_class_methods_are_kept_nested_inside_the_class() {
+        let source = r#"
+class Foo {
+    bar() {
+        return 1;
+    }
+}
+"#;
+        let outline =
+            extract_outline("javascript", source).expect("javascript grammar is supported");
+        assert!(outline.contains("class Foo"));
+        assert_eq!(outline.matches("bar()").count(), 1);
+    }
+
+    #[test]
+    fn typescript_interface_members_are_kept_nested_inside_the_interface() {
+        let source = r#"
+interface Foo {
+    id: number;
+    greet(name: string): void;
+}
+"#;
+        let outline =
+            extract_outline("typescript", source).expect("typescript grammar is supported");
+        assert!(outline.contains("interface Foo"));
+        assert!(outline.contains("id: number"));
+        assert!(outline.contains("greet(name: string): void"));
+    }
+
+    #[test]
+    fn python_class_methods_are_kept_once_nested_inside_the_class() {
+        let source = "class Foo:\n    def bar(self):\n        local = 1\n        return local\n";
+        let outline = extract_outline("python", source).expect("python grammar is supported");
+        assert_eq!(outline.matches("def bar").count(), 1);
+        assert!(outline.contains("class Foo"));
+        assert!(!outline.contains("local = 1"));
+    }
+
+    #[test]
+    fn rust_doc_comment_survives_an_attribute_in_between() {
+        let source = r#"
+/// Greets the caller.
+#[wasm_bindgen]
+pub fn greet() {
+    println!("hi");
+}
+"#;
+        let outline = extract_outline("rust", source).expect("rust grammar is supported");
+        assert!(outline.contains("/// Greets the caller."));
+        assert!(outline.contains("#[wasm_bindgen]"));
+    }
+
+    #[test]
+    fn unsupported_language_returns_none() {
+        assert!(extract_outline("markdown", "# hi").is_none());
+    }
+}